@@ -71,7 +71,85 @@ fn main() {
         .subcommand(
             App::new("cat-file")
                 .about("print object knowing it hash")
-                .arg(Arg::new("hash").about("hash to print").required(true)),
+                .arg(Arg::new("hash").about("hash to print").required(true))
+                .arg(
+                    Arg::new("type")
+                        .short('t')
+                        .about("print the object's type")
+                        .conflicts_with_all(&["size", "pretty"]),
+                )
+                .arg(
+                    Arg::new("size")
+                        .short('s')
+                        .about("print the object's size in bytes")
+                        .conflicts_with_all(&["type", "pretty"]),
+                )
+                .arg(
+                    Arg::new("pretty")
+                        .short('p')
+                        .about("pretty-print the object's content")
+                        .conflicts_with_all(&["type", "size"]),
+                ),
+        )
+        .subcommand(
+            App::new("update-index")
+                .about("stage files into the index")
+                .arg(
+                    Arg::new("path")
+                        .about("paths to stage")
+                        .required(true)
+                        .multiple_values(true),
+                ),
+        )
+        .subcommand(
+            App::new("ls-files")
+                .about("print the staged index, sorted by path"),
+        )
+        .subcommand(
+            App::new("commit-tree")
+                .about("create a commit object from a tree")
+                .arg(Arg::new("tree").about("tree oid").required(true))
+                .arg(
+                    Arg::new("parent")
+                        .short('p')
+                        .long("parent")
+                        .about("parent commit oid")
+                        .takes_value(true),
+                )
+                .arg(
+                    Arg::new("message")
+                        .short('m')
+                        .long("message")
+                        .about("commit message")
+                        .takes_value(true)
+                        .required(true),
+                ),
+        )
+        .subcommand(
+            App::new("commit")
+                .about("record a commit of the current index")
+                .arg(
+                    Arg::new("message")
+                        .short('m')
+                        .long("message")
+                        .about("commit message")
+                        .takes_value(true)
+                        .required(true),
+                ),
+        )
+        .subcommand(
+            App::new("diff")
+                .about("compare two trees or commits")
+                .arg(Arg::new("base").about("base tree or commit oid").required(true))
+                .arg(Arg::new("head").about("head tree or commit oid").required(true))
+                .arg(
+                    Arg::new("color")
+                        .long("color")
+                        .about("when to colorize output")
+                        .takes_value(true)
+                        .possible_values(&["always", "auto", "never"])
+                        .default_value("auto"),
+                ),
         )
         .subcommand(
             App::new("clone")
@@ -111,6 +189,8 @@ fn main() {
         )
         .get_matches();
 
+    let store = LooseFsStore::new(RGIT_DIR);
+
     // The most common way to handle subcommands is via a combined approach using
     // `ArgMatches::subcommand` which returns a tuple of both the name and matches
     match matches.subcommand() {
@@ -143,25 +223,80 @@ fn main() {
             );
         }
         Some(("init", _)) => {
-            // Now we have a reference to add's matches
-            println!("init a repo");
-            data();
+            match data() {
+                Ok(()) => println!("Initialized empty rgit repository"),
+                Err(e) => eprintln!("error: {}", e),
+            }
         }
         Some(("hash-object", hash_matches)) => {
             // Now we have a reference to clone's matches
             let file = hash_matches.value_of("file").unwrap();
             println!("Hashing {}", file);
-            let mut data = fs::read(file);
-            hash_object(data.unwrap(), None);
+            match fs::read(file).map_err(Box::<dyn Error>::from).and_then(|data| hash_object(&store, data, None)) {
+                Ok(oid) => println!("{}", oid),
+                Err(e) => eprintln!("error: {}", e),
+            }
         }
         Some(("cat-file", hash_matches)) => {
-            // Now we have a reference to clone's matches
             let hash = hash_matches.value_of("hash").unwrap();
-            println!("display file {}", hash);
-            cat_file(hash);
+            if hash_matches.is_present("type") {
+                match get_object(&store, hash, None) {
+                    Ok((type_object, _)) => println!("{}", type_object),
+                    Err(e) => eprintln!("error: {}", e),
+                }
+            } else if hash_matches.is_present("size") {
+                match get_object(&store, hash, None) {
+                    Ok((_, content)) => println!("{}", content.len()),
+                    Err(e) => eprintln!("error: {}", e),
+                }
+            } else {
+                cat_file(&store, hash);
+            }
+        }
+        Some(("write-tree", _)) => {
+            match write_tree(&store) {
+                Ok(oid) => println!("{}", oid),
+                Err(e) => eprintln!("error: {}", e),
+            }
+        }
+        Some(("update-index", update_index_matches)) => {
+            let paths: Vec<&str> = update_index_matches.values_of("path").unwrap().collect();
+            if let Err(e) = update_index(&store, &paths) {
+                eprintln!("error: {}", e);
+            }
+        }
+        Some(("ls-files", _)) => {
+            if let Err(e) = ls_files(&store) {
+                eprintln!("error: {}", e);
+            }
+        }
+        Some(("commit-tree", commit_tree_matches)) => {
+            let tree = commit_tree_matches.value_of("tree").unwrap();
+            let parent = commit_tree_matches.value_of("parent");
+            let message = commit_tree_matches.value_of("message").unwrap();
+            match commit_tree(&store, tree, parent, message) {
+                Ok(oid) => println!("{}", oid),
+                Err(e) => eprintln!("error: {}", e),
+            }
         }
-        Some(("write-tree", hash_matches)) => {
-            write_tree(Path::new("src/"));
+        Some(("commit", commit_matches)) => {
+            let message = commit_matches.value_of("message").unwrap();
+            match commit(&store, message) {
+                Ok(oid) => println!("{}", oid),
+                Err(e) => eprintln!("error: {}", e),
+            }
+        }
+        Some(("diff", diff_matches)) => {
+            let base = diff_matches.value_of("base").unwrap();
+            let head = diff_matches.value_of("head").unwrap();
+            let color = match diff_matches.value_of("color").unwrap_or("auto") {
+                "always" => true,
+                "never" => false,
+                _ => atty::is(atty::Stream::Stdout),
+            };
+            if let Err(e) = diff(&store, base, head, color) {
+                eprintln!("error: {}", e);
+            }
         }
 
         None => println!("No subcommand was used"), // If no subcommand was used it'll match the tuple ("", None)
@@ -172,78 +307,648 @@ fn main() {
 }
 
 static RGIT_DIR: &str = ".rgit";
-static RGIT_DIR_OBJECT: &str = ".rgit/objects";
 
-fn data() -> std::io::Result<()> {
-    fs::create_dir(RGIT_DIR)
+use std::error::Error;
+
+// Lays down the full repository skeleton, matching what git's own `init` creates. Uses
+// `create_dir_all` and only writes a file if it's missing, so re-running `init` on an
+// already-initialized repo fills in anything missing instead of erroring out.
+fn data() -> Result<(), Box<dyn Error>> {
+    fs::create_dir_all(format!("{}/objects", RGIT_DIR))?;
+    fs::create_dir_all(format!("{}/refs/heads", RGIT_DIR))?;
+    fs::create_dir_all(format!("{}/refs/tags", RGIT_DIR))?;
+    fs::create_dir_all(format!("{}/info", RGIT_DIR))?;
+
+    let head_path = format!("{}/HEAD", RGIT_DIR);
+    if !Path::new(&head_path).exists() {
+        fs::write(&head_path, "ref: refs/heads/master\n")?;
+    }
+
+    let config_path = format!("{}/config", RGIT_DIR);
+    if !Path::new(&config_path).exists() {
+        fs::write(
+            &config_path,
+            "[core]\n\trepositoryformatversion = 0\n\tfilemode = true\n\tbare = false\n",
+        )?;
+    }
+
+    let description_path = format!("{}/description", RGIT_DIR);
+    if !Path::new(&description_path).exists() {
+        fs::write(
+            &description_path,
+            "Unnamed repository; edit this file to name it for gitweb.\n",
+        )?;
+    }
+
+    let exclude_path = format!("{}/info/exclude", RGIT_DIR);
+    if !Path::new(&exclude_path).exists() {
+        fs::write(
+            &exclude_path,
+            "# rgit ls-files --others --exclude-from=.rgit/info/exclude\n",
+        )?;
+    }
+
+    Ok(())
 }
+
 use sha1::{Digest, Sha1};
-use std::error::Error;
+use std::io::Read;
+use flate2::read::ZlibDecoder;
+use flate2::write::ZlibEncoder;
+use flate2::Compression;
 
-fn hash_object(mut data:Vec<u8> , type_object: Option<&str>) -> Result<String, Box<dyn Error>> {
-    // fs::create_dir(RGIT_DIR_OBJECT)?;
-    let begin:Vec<u8> = [type_object.map(str::as_bytes).unwrap_or(b"blob"), b"\x00"].concat();
-    data.splice(0..0, begin);
-    let mut hasher = Sha1::new();
-    hasher.update(&data);
-    println!("{:?}", data);
-    let hash = hex::encode(hasher.finalize());
-    let path = format!("{}/objects/{}", RGIT_DIR, hash);
-    let mut file = File::create(path)?;
-    file.write(&data)?;
-    Ok(hash)
-}
-
-fn cat_file(hash: &str) {
-    println!(
-        "print file {}",
-        String::from_utf8_lossy(&get_object(hash, None).expect("string"))
-    );
-}
-
-fn get_object(oid: &str, expected: Option<&str>) -> io::Result<Vec<u8>> {
-    let file_path = format!("{}/objects/{}", RGIT_DIR, oid);
-    let data = fs::read(file_path)?;
-    let mut split_iter = io::Cursor::new(&data).split(b'\x00').map(|l| l.unwrap());
-    let type_object = split_iter.next().unwrap();
-    if String::from_utf8(type_object.clone()).expect("") != expected.unwrap_or("blob") {
-        panic!("Expected {}, got {:?}", expected.unwrap(), type_object);
-    }
-    Ok(data)
-}
-
-fn write_tree(dir: &Path) -> Result<String, Box<dyn Error>> {
-    let mut entries = vec![];
-    if dir.is_dir() {
-        for entry in fs::read_dir(dir)? {
-            let entry = entry?;
-            let path = entry.path();
-            let path_to_string = path.to_str().unwrap();
-            let _type;
-            let oid;
-
-            if path_to_string.contains(".ugit") {
-                continue;
-            }
-            if path.is_dir() {
-                //dbg!(&path);
-                _type = "tree";
-                oid = write_tree(&path)?;
-            } else {
-                //dbg!("file", &path);
-                _type = "blob";
-                oid = hash_object(fs::read(&path)?, None)?;
+// Decouples object storage from the plumbing commands, so `hash-object`/`cat-file`/
+// `write-tree`/commit logic don't know or care that objects currently live as loose,
+// zlib-compressed files under `.rgit/objects` - a future packed or in-memory backend only
+// needs a new `ObjectStore` impl.
+trait ObjectStore {
+    fn put(&self, type_object: &str, data: &[u8]) -> Result<String, Box<dyn Error>>;
+    fn get(&self, oid: &str) -> Result<(String, Vec<u8>), Box<dyn Error>>;
+    // The repository root this store is rooted at, so sibling state that isn't an object
+    // (the index, HEAD, refs) can be kept under the same root instead of hardcoding `.rgit`.
+    fn root(&self) -> &str;
+}
+
+struct LooseFsStore {
+    root: String,
+}
+
+impl LooseFsStore {
+    fn new(root: impl Into<String>) -> Self {
+        LooseFsStore { root: root.into() }
+    }
+}
+
+impl ObjectStore for LooseFsStore {
+    fn put(&self, type_object: &str, data: &[u8]) -> Result<String, Box<dyn Error>> {
+        let header = format!("{} {}\x00", type_object, data.len());
+        let mut full = header.into_bytes();
+        full.extend_from_slice(data);
+
+        let mut hasher = Sha1::new();
+        hasher.update(&full);
+        let hash = hex::encode(hasher.finalize());
+
+        let mut encoder = ZlibEncoder::new(Vec::new(), Compression::default());
+        encoder.write_all(&full)?;
+        let compressed = encoder.finish()?;
+
+        let object_dir = format!("{}/objects/{}", self.root, &hash[..2]);
+        fs::create_dir_all(&object_dir)?;
+        let path = format!("{}/{}", object_dir, &hash[2..]);
+        let mut file = File::create(path)?;
+        file.write_all(&compressed)?;
+        Ok(hash)
+    }
+
+    fn get(&self, oid: &str) -> Result<(String, Vec<u8>), Box<dyn Error>> {
+        if oid.len() < 2 || !oid.is_char_boundary(2) || !oid.chars().all(|c| c.is_ascii_hexdigit()) {
+            return Err(format!("invalid object id {:?}: expected a hex oid", oid).into());
+        }
+        let file_path = format!("{}/objects/{}/{}", self.root, &oid[..2], &oid[2..]);
+        let compressed = fs::read(file_path)?;
+        let mut decoder = ZlibDecoder::new(&compressed[..]);
+        let mut data = Vec::new();
+        decoder.read_to_end(&mut data)?;
+
+        let null_pos = data
+            .iter()
+            .position(|&b| b == 0)
+            .ok_or(format!("object {} has no header", oid))?;
+        let header = String::from_utf8(data[..null_pos].to_vec())?;
+        let mut header_parts = header.splitn(2, ' ');
+        let type_object = header_parts
+            .next()
+            .ok_or(format!("object {} has a malformed header", oid))?
+            .to_string();
+        let size: usize = header_parts
+            .next()
+            .ok_or(format!("object {} has a malformed header", oid))?
+            .parse()?;
+
+        let content = data[null_pos + 1..].to_vec();
+        if content.len() != size {
+            return Err(format!(
+                "object {} is corrupt: header says {} bytes, got {}",
+                oid,
+                size,
+                content.len()
+            )
+            .into());
+        }
+
+        Ok((type_object, content))
+    }
+
+    fn root(&self) -> &str {
+        &self.root
+    }
+}
+
+fn hash_object(
+    store: &dyn ObjectStore,
+    data: Vec<u8>,
+    type_object: Option<&str>,
+) -> Result<String, Box<dyn Error>> {
+    store.put(type_object.unwrap_or("blob"), &data)
+}
 
+// `-p`/default cat-file mode: pretty-print by type. Blob and commit content print as-is (raw
+// bytes, and the header+message text); tree content is re-rendered by `print_tree_pretty`.
+fn cat_file(store: &dyn ObjectStore, hash: &str) {
+    match get_object(store, hash, None) {
+        Ok((type_object, content)) => match type_object.as_str() {
+            "blob" => {
+                if let Err(e) = io::stdout().write_all(&content) {
+                    eprintln!("error: {}", e);
+                }
             }
-            let filename = path.into_os_string().into_string().unwrap();
-            entries.push((filename, oid, _type))
+            "tree" => print_tree_pretty(&content),
+            "commit" => print!("{}", String::from_utf8_lossy(&content)),
+            other => eprintln!("error: cat-file -p: unknown object type {}", other),
+        },
+        Err(e) => eprintln!("error: {}", e),
+    }
+}
+
+// Renders a tree object's `mode oid name` lines as a `mode type oid\tname` listing, deriving
+// the type from the mode (our only sub-tree marker is the "40000" directory mode) the way
+// `git cat-file -p` shows `ls-tree`-style rows rather than the raw object bytes.
+fn print_tree_pretty(content: &[u8]) {
+    let text = String::from_utf8_lossy(content);
+    for line in text.lines() {
+        let mut parts = line.splitn(3, ' ');
+        let mode = parts.next().unwrap_or("");
+        let oid = parts.next().unwrap_or("");
+        let name = parts.next().unwrap_or("");
+        let kind = if mode == "40000" { "tree" } else { "blob" };
+        println!("{} {} {}\t{}", mode, kind, oid, name);
+    }
+}
+
+// Fetches `oid` from `store` and, when `expected` is given, enforces its type matches the
+// caller's assumption; pass None when the caller only wants the type or size, as printed by
+// `cat-file -t`/`-s`.
+fn get_object(
+    store: &dyn ObjectStore,
+    oid: &str,
+    expected: Option<&str>,
+) -> Result<(String, Vec<u8>), Box<dyn Error>> {
+    let (type_object, content) = store.get(oid)?;
+    if let Some(expected) = expected {
+        if type_object != expected {
+            return Err(format!("expected object of type {}, got {}", expected, type_object).into());
+        }
+    }
+    Ok((type_object, content))
+}
+
+use std::collections::BTreeMap;
+
+#[derive(Clone)]
+struct IndexEntry {
+    mode: String,
+    oid: String,
+    path: String,
+}
+
+fn index_path(root: &str) -> String {
+    format!("{}/index", root)
+}
+
+// The index is stored as one `mode oid path` line per staged file, sorted by path, under the
+// same root the object store is rooted at. This is not git's binary index format, but it
+// plays the same role: a snapshot of what the next commit will contain, independent of
+// what's currently on disk.
+fn read_index(root: &str) -> Result<Vec<IndexEntry>, Box<dyn Error>> {
+    let path = index_path(root);
+    if !Path::new(&path).exists() {
+        return Ok(Vec::new());
+    }
+    let file = File::open(path)?;
+    let mut entries = Vec::new();
+    for line in io::BufReader::new(file).lines() {
+        let line = line?;
+        if line.is_empty() {
+            continue;
+        }
+        let mut parts = line.splitn(3, ' ');
+        let mode = parts.next().ok_or("malformed index entry")?.to_string();
+        let oid = parts.next().ok_or("malformed index entry")?.to_string();
+        let path = parts.next().ok_or("malformed index entry")?.to_string();
+        entries.push(IndexEntry { mode, oid, path });
+    }
+    Ok(entries)
+}
+
+fn write_index(root: &str, entries: &[IndexEntry]) -> Result<(), Box<dyn Error>> {
+    let mut sorted = entries.to_vec();
+    sorted.sort_by(|a, b| a.path.cmp(&b.path));
+    let mut content = String::new();
+    for entry in &sorted {
+        content.push_str(&format!("{} {} {}\n", entry.mode, entry.oid, entry.path));
+    }
+    fs::write(index_path(root), content)?;
+    Ok(())
+}
+
+fn update_index(store: &dyn ObjectStore, paths: &[&str]) -> Result<(), Box<dyn Error>> {
+    let mut entries = read_index(store.root())?;
+    for &path in paths {
+        let oid = hash_object(store, fs::read(path)?, None)?;
+        match entries.iter_mut().find(|e| e.path == path) {
+            Some(entry) => entry.oid = oid,
+            None => entries.push(IndexEntry {
+                mode: "100644".to_string(),
+                oid,
+                path: path.to_string(),
+            }),
+        }
+    }
+    write_index(store.root(), &entries)
+}
+
+fn ls_files(store: &dyn ObjectStore) -> Result<(), Box<dyn Error>> {
+    let mut entries = read_index(store.root())?;
+    entries.sort_by(|a, b| a.path.cmp(&b.path));
+    for entry in entries {
+        println!("{} {} {}", entry.mode, entry.oid, entry.path);
+    }
+    Ok(())
+}
+
+// Builds the tree object graph for the current index, rather than walking the filesystem:
+// the staged snapshot is what gets written, not whatever happens to be in the working tree.
+fn write_tree(store: &dyn ObjectStore) -> Result<String, Box<dyn Error>> {
+    let entries = read_index(store.root())?;
+    let rows: Vec<(Vec<String>, String, String)> = entries
+        .into_iter()
+        .map(|e| (e.path.split('/').map(String::from).collect(), e.mode, e.oid))
+        .collect();
+    build_tree(store, &rows)
+}
+
+fn build_tree(
+    store: &dyn ObjectStore,
+    entries: &[(Vec<String>, String, String)],
+) -> Result<String, Box<dyn Error>> {
+    let mut files = Vec::new();
+    let mut dirs: BTreeMap<String, Vec<(Vec<String>, String, String)>> = BTreeMap::new();
+
+    for (components, mode, oid) in entries {
+        if components.len() == 1 {
+            files.push((components[0].clone(), mode.clone(), oid.clone()));
+        } else {
+            dirs.entry(components[0].clone())
+                .or_insert_with(Vec::new)
+                .push((components[1..].to_vec(), mode.clone(), oid.clone()));
         }
     }
 
     let mut tree = String::new();
-    for (filename, oid, _type) in entries.iter() {
-        tree.push_str(&format!("{} {} {}\n", _type, oid, filename));
+    for (name, mode, oid) in files {
+        tree.push_str(&format!("{} {} {}\n", mode, oid, name));
+    }
+    for (name, children) in dirs {
+        let oid = build_tree(store, &children)?;
+        tree.push_str(&format!("40000 {} {}\n", oid, name));
+    }
+
+    hash_object(store, tree.as_bytes().to_vec(), Some("tree"))
+}
+
+use std::env;
+use std::time::{SystemTime, UNIX_EPOCH};
+
+// Resolves a ref relative to `root`, following `ref: <path>` indirection (e.g. HEAD pointing
+// at refs/heads/master) through as many levels as there are, and returns the oid it ultimately names, or None if it
+// doesn't exist yet.
+fn get_ref(root: &str, name: &str) -> Result<Option<String>, Box<dyn Error>> {
+    let path = format!("{}/{}", root, name);
+    if !Path::new(&path).exists() {
+        return Ok(None);
+    }
+    let content = fs::read_to_string(&path)?.trim().to_string();
+    match content.strip_prefix("ref: ") {
+        Some(target) => get_ref(root, target),
+        None => Ok(Some(content)),
+    }
+}
+
+fn update_ref(root: &str, name: &str, oid: &str) -> Result<(), Box<dyn Error>> {
+    let path = format!("{}/{}", root, name);
+    if let Some(parent) = Path::new(&path).parent() {
+        fs::create_dir_all(parent)?;
+    }
+    fs::write(path, format!("{}\n", oid))?;
+    Ok(())
+}
+
+// HEAD's target ref, e.g. "refs/heads/master", without resolving it to an oid.
+fn head_ref_path(root: &str) -> Result<String, Box<dyn Error>> {
+    let content = fs::read_to_string(format!("{}/HEAD", root))?.trim().to_string();
+    content
+        .strip_prefix("ref: ")
+        .map(|s| s.to_string())
+        .ok_or_else(|| "HEAD is detached, refusing to commit".into())
+}
+
+// Author/committer identity, read from RGIT_AUTHOR_NAME/RGIT_AUTHOR_EMAIL with a fallback so
+// commit-tree works out of the box; a .rgit/config file can replace this later.
+fn author_identity() -> (String, u64, String) {
+    let name = env::var("RGIT_AUTHOR_NAME").unwrap_or_else(|_| "rgit".to_string());
+    let email = env::var("RGIT_AUTHOR_EMAIL").unwrap_or_else(|_| "rgit@localhost".to_string());
+    let timestamp = SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .unwrap_or_default()
+        .as_secs();
+    (format!("{} <{}>", name, email), timestamp, "+0000".to_string())
+}
+
+fn commit_tree(
+    store: &dyn ObjectStore,
+    tree_oid: &str,
+    parent: Option<&str>,
+    message: &str,
+) -> Result<String, Box<dyn Error>> {
+    let (identity, timestamp, tz) = author_identity();
+    let mut content = format!("tree {}\n", tree_oid);
+    if let Some(parent_oid) = parent {
+        content.push_str(&format!("parent {}\n", parent_oid));
+    }
+    content.push_str(&format!("author {} {} {}\n", identity, timestamp, tz));
+    content.push_str(&format!("committer {} {} {}\n", identity, timestamp, tz));
+    content.push_str(&format!("\n{}\n", message));
+
+    hash_object(store, content.into_bytes(), Some("commit"))
+}
+
+// Writes the index as a tree, commits it with the current HEAD as parent, and moves the
+// branch HEAD points at forward to the new commit.
+fn commit(store: &dyn ObjectStore, message: &str) -> Result<String, Box<dyn Error>> {
+    let tree_oid = write_tree(store)?;
+    let parent = get_ref(store.root(), "HEAD")?;
+    let commit_oid = commit_tree(store, &tree_oid, parent.as_deref(), message)?;
+    update_ref(store.root(), &head_ref_path(store.root())?, &commit_oid)?;
+    Ok(commit_oid)
+}
+
+use std::collections::BTreeSet;
+
+// `base`/`head` may each be a tree oid or a commit oid; resolve a commit down to the tree it
+// points at so `diff` can accept either, the way `git diff` does.
+fn resolve_to_tree(store: &dyn ObjectStore, oid: &str) -> Result<String, Box<dyn Error>> {
+    let (type_object, content) = get_object(store, oid, None)?;
+    match type_object.as_str() {
+        "tree" => Ok(oid.to_string()),
+        "commit" => {
+            let content = String::from_utf8(content)?;
+            let tree_line = content
+                .lines()
+                .find(|line| line.starts_with("tree "))
+                .ok_or_else(|| format!("commit {} has no tree line", oid))?;
+            Ok(tree_line["tree ".len()..].to_string())
+        }
+        other => Err(format!("object {} is a {}, expected a tree or commit", oid, other).into()),
+    }
+}
+
+// Recursively flattens a tree object into a path -> blob oid map, descending into sub-trees
+// so the result only ever contains blobs.
+fn get_tree(store: &dyn ObjectStore, oid: &str) -> Result<BTreeMap<String, String>, Box<dyn Error>> {
+    get_tree_at(store, oid, "")
+}
+
+fn get_tree_at(
+    store: &dyn ObjectStore,
+    oid: &str,
+    prefix: &str,
+) -> Result<BTreeMap<String, String>, Box<dyn Error>> {
+    let (_, content) = get_object(store, oid, Some("tree"))?;
+    let content = String::from_utf8(content)?;
+
+    let mut entries = BTreeMap::new();
+    for line in content.lines() {
+        let mut parts = line.splitn(3, ' ');
+        let mode = parts.next().ok_or("malformed tree entry")?;
+        let entry_oid = parts.next().ok_or("malformed tree entry")?;
+        let name = parts.next().ok_or("malformed tree entry")?;
+        let path = if prefix.is_empty() {
+            name.to_string()
+        } else {
+            format!("{}/{}", prefix, name)
+        };
+
+        if mode == "40000" {
+            entries.extend(get_tree_at(store, entry_oid, &path)?);
+        } else {
+            entries.insert(path, entry_oid.to_string());
+        }
+    }
+    Ok(entries)
+}
+
+fn print_colored(code: &str, color: bool, line: &str) {
+    if color {
+        println!("\x1b[{}m{}\x1b[0m", code, line);
+    } else {
+        println!("{}", line);
+    }
+}
+
+// A simple line-oriented diff: lines only present in `base` are removed, lines only present
+// in `head` are added. This doesn't align moved/reordered lines like a real LCS diff would,
+// but it's enough to see what changed in a blob.
+fn diff_blobs(
+    store: &dyn ObjectStore,
+    path: &str,
+    base_oid: &str,
+    head_oid: &str,
+    color: bool,
+) -> Result<(), Box<dyn Error>> {
+    let (_, base_content) = get_object(store, base_oid, Some("blob"))?;
+    let (_, head_content) = get_object(store, head_oid, Some("blob"))?;
+    let base_text = String::from_utf8_lossy(&base_content);
+    let head_text = String::from_utf8_lossy(&head_content);
+    let base_lines: Vec<&str> = base_text.lines().collect();
+    let head_lines: Vec<&str> = head_text.lines().collect();
+
+    println!("diff --rgit a/{} b/{}", path, path);
+    for line in &base_lines {
+        if !head_lines.contains(line) {
+            print_colored("31", color, &format!("-{}", line));
+        }
+    }
+    for line in &head_lines {
+        if !base_lines.contains(line) {
+            print_colored("32", color, &format!("+{}", line));
+        }
+    }
+    Ok(())
+}
+
+// Compares the trees reachable from `base` and `head` (each a tree or commit oid) and reports
+// additions, removals, and modifications per path.
+fn diff(store: &dyn ObjectStore, base: &str, head: &str, color: bool) -> Result<(), Box<dyn Error>> {
+    let base_map = get_tree(store, &resolve_to_tree(store, base)?)?;
+    let head_map = get_tree(store, &resolve_to_tree(store, head)?)?;
+
+    let mut paths = BTreeSet::new();
+    paths.extend(base_map.keys().cloned());
+    paths.extend(head_map.keys().cloned());
+
+    for path in paths {
+        match (base_map.get(&path), head_map.get(&path)) {
+            (Some(_), None) => print_colored("31", color, &format!("removed {}", path)),
+            (None, Some(_)) => print_colored("32", color, &format!("added {}", path)),
+            (Some(base_oid), Some(head_oid)) if base_oid != head_oid => {
+                diff_blobs(store, &path, base_oid, head_oid, color)?;
+            }
+            _ => {}
+        }
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // A fresh `LooseFsStore` rooted at a throwaway directory under `std::env::temp_dir()`, so
+    // these tests exercise the trait without touching any real `.rgit`.
+    fn temp_store(name: &str) -> (LooseFsStore, std::path::PathBuf) {
+        let root = std::env::temp_dir().join(format!("rgit-test-{}-{}", std::process::id(), name));
+        let _ = fs::remove_dir_all(&root);
+        fs::create_dir_all(&root).unwrap();
+        (LooseFsStore::new(root.to_str().unwrap()), root)
+    }
+
+    #[test]
+    fn put_then_get_roundtrips_type_and_content() {
+        let (store, root) = temp_store("roundtrip");
+        let oid = store.put("blob", b"hello world").unwrap();
+        let (type_object, content) = store.get(&oid).unwrap();
+        assert_eq!(type_object, "blob");
+        assert_eq!(content, b"hello world");
+        fs::remove_dir_all(&root).unwrap();
+    }
+
+    #[test]
+    fn get_rejects_oid_shorter_than_the_fan_out_prefix() {
+        let (store, root) = temp_store("short-oid");
+        assert!(store.get("a").is_err());
+        assert!(store.get("").is_err());
+        let _ = fs::remove_dir_all(&root);
+    }
+
+    #[test]
+    fn get_object_enforces_expected_type() {
+        let (store, root) = temp_store("type-mismatch");
+        let oid = store.put("blob", b"payload").unwrap();
+        assert!(get_object(&store, &oid, Some("tree")).is_err());
+        assert!(get_object(&store, &oid, Some("blob")).is_ok());
+        fs::remove_dir_all(&root).unwrap();
     }
 
-    hash_object(tree.as_bytes().to_vec(), Some("tree"))
+    // These previously couldn't be tested at all without writing into the real `.rgit` of
+    // whatever directory `cargo test` ran in - now that the index and refs are rooted at the
+    // store's own root, write_tree/commit/diff can run entirely inside a temp dir.
+
+    #[test]
+    fn write_tree_builds_nested_tree_from_index() {
+        let (store, root) = temp_store("write-tree");
+        let file_oid = store.put("blob", b"content").unwrap();
+        let entries = vec![
+            IndexEntry {
+                mode: "100644".to_string(),
+                oid: file_oid.clone(),
+                path: "a.txt".to_string(),
+            },
+            IndexEntry {
+                mode: "100644".to_string(),
+                oid: file_oid.clone(),
+                path: "dir/b.txt".to_string(),
+            },
+        ];
+        write_index(store.root(), &entries).unwrap();
+
+        let tree_oid = write_tree(&store).unwrap();
+        let flattened = get_tree(&store, &tree_oid).unwrap();
+        assert_eq!(flattened.get("a.txt"), Some(&file_oid));
+        assert_eq!(flattened.get("dir/b.txt"), Some(&file_oid));
+        fs::remove_dir_all(&root).unwrap();
+    }
+
+    #[test]
+    fn commit_chains_parents_through_head() {
+        let (store, root) = temp_store("commit");
+        fs::write(format!("{}/HEAD", store.root()), "ref: refs/heads/master\n").unwrap();
+
+        let v1 = store.put("blob", b"v1").unwrap();
+        write_index(
+            store.root(),
+            &[IndexEntry {
+                mode: "100644".to_string(),
+                oid: v1,
+                path: "f.txt".to_string(),
+            }],
+        )
+        .unwrap();
+        let first = commit(&store, "first").unwrap();
+        assert_eq!(
+            get_ref(store.root(), "HEAD").unwrap().as_deref(),
+            Some(first.as_str())
+        );
+
+        let v2 = store.put("blob", b"v2").unwrap();
+        write_index(
+            store.root(),
+            &[IndexEntry {
+                mode: "100644".to_string(),
+                oid: v2,
+                path: "f.txt".to_string(),
+            }],
+        )
+        .unwrap();
+        let second = commit(&store, "second").unwrap();
+
+        let (_, content) = store.get(&second).unwrap();
+        let content = String::from_utf8(content).unwrap();
+        assert!(content.lines().any(|line| line == format!("parent {}", first)));
+
+        fs::remove_dir_all(&root).unwrap();
+    }
+
+    #[test]
+    fn diff_compares_two_commits_by_path() {
+        let (store, root) = temp_store("diff");
+        fs::write(format!("{}/HEAD", store.root()), "ref: refs/heads/master\n").unwrap();
+
+        let v1 = store.put("blob", b"v1").unwrap();
+        write_index(
+            store.root(),
+            &[IndexEntry {
+                mode: "100644".to_string(),
+                oid: v1,
+                path: "f.txt".to_string(),
+            }],
+        )
+        .unwrap();
+        let base = commit(&store, "base").unwrap();
+
+        let v2 = store.put("blob", b"v2").unwrap();
+        write_index(
+            store.root(),
+            &[IndexEntry {
+                mode: "100644".to_string(),
+                oid: v2,
+                path: "f.txt".to_string(),
+            }],
+        )
+        .unwrap();
+        let head = commit(&store, "head").unwrap();
+
+        assert!(diff(&store, &base, &head, false).is_ok());
+        fs::remove_dir_all(&root).unwrap();
+    }
 }
\ No newline at end of file